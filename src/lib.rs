@@ -1,7 +1,7 @@
 /// This crate provides an implementation of the Keccak (SHA-3) cryptographic hash function family.
-/// 
+///
 /// # Simple Overview of the Sha3 Program
-/// 
+///
 /// The Sha3 program is a Rust crate that provides an implementation of the Keccak (SHA-3)
 /// cryptographic hash function family. The crate provides several Hash functions with
 /// different output lengths (224, 256, 384, 512 bits). The hash functions are created using
@@ -13,14 +13,14 @@
 /// the hash output. The hash functions can be used to hash strings, files, or any other
 /// binary data.
 ///
-/// The crate also provides utility functions to convert bytes to bits and vice versa.
-/// These functions are used internally by the Hash functions but can also be used by
-/// other parts of the program if needed.
+/// The crate also provides variable-length output via the SHAKE extendable-output
+/// functions (`shake128`/`shake256`).
 ///
 /// The crate uses the Keccak permutation, which is the core of the SHA-3 algorithm.
 /// The Keccak permutation is a sponge function that can be used to transform any
-/// input data of any length into a fixed-size output. The crate defines the Keccak
-/// permutation as a macro that can be used to create other sponge functions.
+/// input data of any length into a fixed-size output. Internally the state is kept as
+/// a `[u64; 25]` array of lanes and the step mappings operate on whole 64-bit words,
+/// which is far faster than the per-bit representation used by naive implementations.
 ///
 /// The crate also provides some example code in the `main.rs` file that demonstrates
 /// how to use the Hash functions to hash strings, files, and multiple inputs.
@@ -34,380 +34,679 @@ const B: usize = 1600;
 /// The lane size (64 bits).
 const W: usize = B / 25;
 
-/// The number of rounds.
-///
-/// The number of rounds is calculated using the number of trailing zeros in the binary
-/// representation of `B`. This ensures that the number of rounds is correct for the
-/// specific Keccak permutation.
-const L: usize = W.trailing_zeros() as usize;
-
 /// Number of bits in a byte.
-///
-/// This constant is used to calculate the number of rounds in the Keccak permutation.
 const U8BITS: usize = u8::BITS as usize;
 
-/// A macro to iterate over the state array.
-///
-/// This macro is used to iterate over the state array in the Keccak permutation. It
-/// allows for more concise code when performing operations on the state array. The macro
-/// takes three identifiers `$x`, `$y`, and `$z` which represent the indices of the state
-/// array. The body of the macro is executed for each iteration.
-#[macro_export]
-macro_rules! iterate {
-    // The macro takes a pattern consisting of three identifiers and a block of code.
-    ($x:ident, $y:ident, $z:ident => $body:block) => {
-        // The macro iterates over the indices of the state array.
-        for $y in 0..5 {
-            for $x in 0..5 {
-                for $z in 0..W {
-                    // The code block is executed for each iteration.
-                    $body
-                }
-            }
-        }
-    };
+/// The lane rotation offsets used by the fused rho + pi step, indexed by the
+/// permutation step `t ∈ 0..24`. Each entry is `((t+1)*(t+2)/2) mod 64`.
+const RHO: [u32; 24] = [
+    1, 3, 6, 10, 15, 21, 28, 36, 45, 55, 2, 14,
+    27, 41, 56, 8, 25, 43, 62, 18, 39, 61, 20, 44,
+];
+
+/// The lane-permutation table used by the fused rho + pi step. `PI[t]` is the
+/// flat lane index that the rolling lane is written to at step `t`.
+const PI: [usize; 24] = [
+    10, 7, 11, 17, 18, 3, 5, 16, 8, 21, 24, 4,
+    15, 23, 19, 13, 12, 2, 20, 14, 22, 9, 6, 1,
+];
+
+/// The round constants XORed into lane 0 by the iota step.
+const RC: [u64; 24] = [
+    0x0000000000000001, 0x0000000000008082, 0x800000000000808a, 0x8000000080008000,
+    0x000000000000808b, 0x0000000080000001, 0x8000000080008081, 0x8000000000008009,
+    0x000000000000008a, 0x0000000000000088, 0x0000000080008009, 0x000000008000000a,
+    0x000000008000808b, 0x800000000000008b, 0x8000000000008089, 0x8000000000008003,
+    0x8000000000008002, 0x8000000000000080, 0x000000000000800a, 0x800000008000000a,
+    0x8000000080008081, 0x8000000000008080, 0x0000000080000001, 0x8000000080008008,
+];
+
+/// The state of the Keccak permutation: 25 lanes of 64 bits each.
+///
+/// The lane at coordinate `(x, y)` is stored at the flat index `x + 5*y`.
+type State = [u64; 25];
+
+/// Returns the mask that keeps a lane restricted to its low `w` bits.
+#[inline]
+fn lane_mask(w: usize) -> u64 {
+    if w == W {
+        u64::MAX
+    } else {
+        (1u64 << w) - 1
+    }
 }
 
-/// Type definition for padding functions.
-type PadFn = fn(isize, isize) -> Vec<bool>;
+/// Rotates the low `w` bits of `x` left by `n`, keeping the result masked.
+///
+/// For the full `w = 64` width this folds down to a plain `rotate_left`.
+#[inline]
+fn rotl_w(x: u64, n: u32, w: usize, mask: u64) -> u64 {
+    let n = n % w as u32;
+    if n == 0 {
+        x & mask
+    } else {
+        ((x << n) | ((x & mask) >> (w as u32 - n))) & mask
+    }
+}
 
-/// Type definition for sponge functions.
-type SpongeFn = fn(&[bool]) -> [bool; B];
+/// The theta step mapping of Keccak.
+///
+/// This function computes the column parities `C[x]`, derives `D[x] = C[x-1] ^
+/// rotl(C[x+1], 1)`, and XORs `D[x]` into every lane of column `x`. Only the
+/// low `w` bits of each lane participate.
+#[inline]
+fn theta(state: &mut State, w: usize, mask: u64) {
+    let mut c = [0u64; 5];
+    for x in 0..5 {
+        c[x] = state[x] ^ state[x + 5] ^ state[x + 10] ^ state[x + 15] ^ state[x + 20];
+    }
 
-/// The state of the Keccak permutation.
-type State = [[[bool; W]; 5]; 5];
+    let mut d = [0u64; 5];
+    for x in 0..5 {
+        d[x] = c[(x + 4) % 5] ^ rotl_w(c[(x + 1) % 5], 1, w, mask);
+    }
 
-/// Creates a new state filled with `false`.
-fn new_state() -> State {
-    [[[false; W]; 5]; 5]
+    for y in 0..5 {
+        for x in 0..5 {
+            state[x + 5 * y] ^= d[x];
+        }
+    }
 }
 
-/// Fills the state array with the provided bits.
-fn fill_state(state: &mut State, bits: &[bool]) {
-    let mut i = 0usize;
-    iterate!(x, y, z => {
-        if i >= bits.len() {
-            return;
+/// The fused rho and pi step mappings of Keccak.
+///
+/// This function rotates each lane by its rho offset and moves it to its pi
+/// destination in a single pass. It walks the 24-step cycle starting from lane
+/// `(1, 0)`, carrying the displaced lane forward in `last`. Because every
+/// supported width divides 64, the offset for width `w` is simply `RHO[t] % w`.
+#[inline]
+fn rho_pi(state: &mut State, w: usize, mask: u64) {
+    let mut last = state[1] & mask;
+    for t in 0..24 {
+        let current = state[PI[t]];
+        state[PI[t]] = rotl_w(last, RHO[t] % w as u32, w, mask);
+        last = current;
+    }
+}
+
+/// The chi step mapping of Keccak.
+///
+/// This function applies `A[x] ^= (!A[x+1]) & A[x+2]` across each of the five
+/// rows of the state, keeping the result masked to the low `w` bits.
+#[inline]
+fn chi(state: &mut State, mask: u64) {
+    for y in 0..5 {
+        let row: [u64; 5] = [
+            state[5 * y],
+            state[5 * y + 1],
+            state[5 * y + 2],
+            state[5 * y + 3],
+            state[5 * y + 4],
+        ];
+        for x in 0..5 {
+            state[x + 5 * y] = (row[x] ^ ((!row[(x + 1) % 5]) & row[(x + 2) % 5])) & mask;
         }
-        state[x][y][z] = bits[i];
-        i += 1;
-    });
+    }
 }
 
-/// Copies the state from `src` to `dest`.
-fn copy_state(dest: &mut State, src: &State) {
-    iterate!(x, y, z => {
-        dest[x][y][z] = src[x][y][z];
-    });
+/// The general Keccak-p\[b\] permutation for any lane width `w ∈ {1,2,4,8,16,32,64}`.
+///
+/// The state is the familiar `[u64; 25]` array, but only the low `w` bits of
+/// each lane are used, so the permutation covers the reduced widths `b = 25w`.
+/// The number of rounds scales as `12 + 2*log2(w)`, the rho offsets are reduced
+/// modulo `w`, and the round constants are truncated to `w` bits. The
+/// full-width `keccak_f` is the `w = 64` specialization of this one
+/// implementation, so the two families can never drift.
+pub fn keccak_p(state: &mut [u64; 25], w: usize) {
+    assert!(
+        w.is_power_of_two() && w <= W,
+        "lane width must be one of 1, 2, 4, 8, 16, 32, 64"
+    );
+    let mask = lane_mask(w);
+    let num_rounds = 12 + 2 * w.trailing_zeros() as usize;
+    for rc in RC.iter().take(num_rounds) {
+        theta(state, w, mask);
+        rho_pi(state, w, mask);
+        chi(state, mask);
+        state[0] ^= rc & mask;
+    }
 }
 
-/// Dumps the state array into a single array of bits.
+/// The Keccak-f\[1600\] permutation function.
 ///
-/// # Returns
+/// This is the hot SHA-3 path: the full-width specialization of [`keccak_p`].
+/// With `w = 64` the mask is `u64::MAX` and `rotl_w` folds to a plain
+/// `rotate_left`, so the generic code compiles down to the same full-speed
+/// word operations a hand-unrolled version would.
+#[inline]
+fn keccak_f(state: &mut State) {
+    keccak_p(state, W);
+}
+
+/// Absorbs a single `rate`-byte block into the state.
 ///
-/// A vector of boolean values representing the state array.
-fn dump_state(state: State) -> [bool; B] {
-    let mut bits = [false; B];
-    let mut i = 0usize;
-    // Iterate over each element in the state array and assign it to the corresponding
-    // element in the bits vector.
-    iterate!(x, y, z => {
-        if i >= bits.len() {
-            return bits;
-        }
-        bits[i] = state[x][y][z];
-        i += 1;
-    });
-    bits
+/// The block is packed little-endian into the leading lanes and XORed into the
+/// state. The rate is always a whole number of lanes for the standard families.
+fn absorb_block(state: &mut State, block: &[u8]) {
+    for (lane, chunk) in block.chunks_exact(U8BITS).enumerate() {
+        state[lane] ^= u64::from_le_bytes(chunk.try_into().unwrap());
+    }
 }
 
-/// The theta step mapping of Keccak.
+/// The sponge construction used in Keccak, operating on bytes.
 ///
-/// This function computes the parity for each column in the state array and then
-/// computes the intermediate array `d` by performing XOR operations on the elements
-/// of the state array. Finally, it modifies the state array by performing XOR operations
-/// on the elements of the `d` array.
-fn theta(state: &mut State) {
-    let mut c = [[false; W]; 5];
-    let mut d = [[false; W]; 5];
+/// This function absorbs `input` one rate-sized block at a time, applies the
+/// multi-rate `pad10*1` padding together with the domain-separation `suffix` to
+/// the final (partial) block, and then squeezes `out_len` bytes out of the
+/// state.
+///
+/// # Arguments
+///
+/// * `rate` - The sponge rate in bytes (`(B - capacity) / 8`).
+/// * `suffix` - The domain-separation byte appended before padding.
+/// * `input` - The message to absorb.
+/// * `out_len` - The desired output length in bytes.
+///
+/// # Returns
+///
+/// A vector of `out_len` bytes squeezed from the sponge.
+fn sponge(rate: usize, suffix: u8, input: &[u8], out_len: usize) -> Vec<u8> {
+    assert!(rate > 0 && rate < B / U8BITS);
+    let mut state: State = [0u64; 25];
+
+    // Absorb every full rate-sized block of the input.
+    let mut offset = 0;
+    while offset + rate <= input.len() {
+        absorb_block(&mut state, &input[offset..offset + rate]);
+        keccak_f(&mut state);
+        offset += rate;
+    }
 
-    // Compute parity for each column
-    for x in 0..5 {
-        for z in 0..W {
-            c[x][z] = state[x][0][z];
-            for y in 1..5 {
-                c[x][z] ^= state[x][y][z];
+    // Absorb the trailing partial block with the suffix and `pad10*1` padding.
+    let mut last = vec![0u8; rate];
+    let remainder = &input[offset..];
+    last[..remainder.len()].copy_from_slice(remainder);
+    last[remainder.len()] ^= suffix;
+    last[rate - 1] ^= 0x80;
+    absorb_block(&mut state, &last);
+    keccak_f(&mut state);
+
+    // Squeeze out the requested number of output bytes.
+    squeeze(&mut state, rate, out_len)
+}
+
+/// Squeezes `out_len` bytes out of a finalized state.
+///
+/// This function reads the leading `rate` bytes of the state little-endian and
+/// re-applies the permutation whenever more output is needed, as required by the
+/// sponge construction.
+fn squeeze(state: &mut State, rate: usize, out_len: usize) -> Vec<u8> {
+    let mut out = Vec::with_capacity(out_len);
+    'squeeze: loop {
+        for lane in state.iter().take(rate / U8BITS) {
+            for byte in lane.to_le_bytes() {
+                if out.len() == out_len {
+                    break 'squeeze;
+                }
+                out.push(byte);
             }
         }
+        keccak_f(state);
     }
+    out
+}
 
-    // Compute the intermediate array `d`
-    for x in 0..5 {
-        for z in 0..W {
-            let x1 = (x + 4) % 5;
-            let z2 = (z + W - 1) % W;
-            d[x][z] = c[x1][z] ^ c[(x + 1) % 5][z2];
+/// The Keccak hash function.
+///
+/// Hashes `input` at the given `capacity` (in bits) using the domain-separation
+/// `suffix`, producing `out_len` output bytes.
+fn keccak(capacity: usize, suffix: u8, input: &[u8], out_len: usize) -> Vec<u8> {
+    sponge((B - capacity) / U8BITS, suffix, input, out_len)
+}
+
+/// A macro to define fixed-length Keccak hash functions.
+///
+/// The `$suffix` byte selects the family: the standard SHA-3 functions pass
+/// `0x06` (the `01` domain-separation bits folded into the first `pad10*1`
+/// bit), while the pre-standardization Keccak functions pass `0x01` (no domain
+/// bits, just the multi-rate padding). Both families are otherwise identical,
+/// so a single code path serves them.
+macro_rules! sha3 {
+    ($name:ident, $n:literal, $suffix:literal) => {
+        /// Computes the fixed-length Keccak hash of the input data.
+        ///
+        /// # Arguments
+        ///
+        /// * `input` - A byte slice containing the data to hash.
+        ///
+        /// # Returns
+        ///
+        /// A fixed-size array containing the hash output.
+        pub fn $name(input: &[u8]) -> [u8; $n / U8BITS] {
+            // Capacity is twice the digest size.
+            let result = keccak($n * 2, $suffix, input, $n / U8BITS);
+            result.try_into().expect("incorrect length")
         }
-    }
+    };
+}
 
-    // Modify the state with `d`
-    iterate!(x, y, z => {
-        state[x][y][z] ^= d[x][z];
-    });
+// Define the standard SHA-3 hash functions (FIPS-202 domain separation).
+sha3!(sha3_224, 224, 0x06);
+sha3!(sha3_256, 256, 0x06);
+sha3!(sha3_384, 384, 0x06);
+sha3!(sha3_512, 512, 0x06);
+
+// Define the legacy Keccak hash functions (pre-standardization padding, as used
+// by Ethereum and other systems that predate FIPS-202).
+sha3!(keccak224, 224, 0x01);
+sha3!(keccak256, 256, 0x01);
+sha3!(keccak384, 384, 0x01);
+sha3!(keccak512, 512, 0x01);
+
+/// A macro to define SHAKE extendable-output functions (XOFs).
+///
+/// Unlike the fixed-length `sha3!` family, a SHAKE function takes the desired
+/// output length (in bytes) as a runtime argument and returns a `Vec<u8>` of
+/// that length. The sponge squeezes as many blocks as are needed to satisfy the
+/// request. The capacity `$c` fixes the security level (256 for SHAKE128, 512
+/// for SHAKE256) and the XOF domain-separation suffix `1111` combines with the
+/// first padding bit into the byte `0x1f`.
+macro_rules! shake {
+    ($name:ident, $c:literal) => {
+        /// Computes the SHAKE extendable-output hash of the input data.
+        ///
+        /// # Arguments
+        ///
+        /// * `input` - A byte slice containing the data to hash.
+        /// * `out_len` - The desired output length in bytes.
+        ///
+        /// # Returns
+        ///
+        /// A `Vec<u8>` of length `out_len` containing the hash output.
+        pub fn $name(input: &[u8], out_len: usize) -> Vec<u8> {
+            keccak($c, 0x1f, input, out_len)
+        }
+    };
 }
 
-/// The rho step mapping of Keccak.
-///
-/// This function performs the permutation of the remaining bits in the state array.
-/// It copies the bit from state`[0][0]` directly and performs permutation of the remaining
-/// bits.
-fn rho(state: &mut State) {
-    let mut new_state = new_state();
+// Define SHAKE extendable-output functions with their respective capacities.
+shake!(shake128, 256);
+shake!(shake256, 512);
+
+/// An incremental Keccak sponge hasher.
+///
+/// `Sha3Hasher` exposes the sponge absorb phase incrementally so that callers
+/// can hash streams of unbounded size without buffering the whole input in
+/// memory. It is parameterized by its capacity, domain-separation suffix, and
+/// output length, with convenience constructors for the standard SHA-3 and
+/// SHAKE families.
+pub struct Sha3Hasher {
+    /// The persistent lane-based sponge state.
+    state: State,
+    /// The sponge rate in bytes.
+    rate: usize,
+    /// The domain-separation suffix appended before padding.
+    suffix: u8,
+    /// The desired output length in bytes.
+    out_len: usize,
+    /// Buffered bytes of the current, not-yet-full block (always `< rate`).
+    buffer: Vec<u8>,
+}
 
-    // Copy the bit from state[0][0] directly
-    for z in 0..W {
-        new_state[0][0][z] = state[0][0][z];
+impl Sha3Hasher {
+    /// Creates a new hasher with the given `capacity` (in bits), domain-separation
+    /// `suffix`, and `out_len` output length (in bytes).
+    pub fn new(capacity: usize, suffix: u8, out_len: usize) -> Self {
+        let rate = (B - capacity) / U8BITS;
+        Sha3Hasher {
+            state: [0u64; 25],
+            rate,
+            suffix,
+            out_len,
+            buffer: Vec::with_capacity(rate),
+        }
     }
 
-    let mut x = 1;
-    let mut y = 0;
+    /// Creates an incremental SHA3-224 hasher.
+    pub fn sha3_224() -> Self {
+        Self::new(448, 0x06, 224 / U8BITS)
+    }
 
-    // Permutation of the remaining bits
-    for t in 0..24 {
-        for z in 0..W {
-            let new_z = (z + (t * (t + 1)) / 2) % W;
-            new_state[x][y][z] = state[x][y][new_z];
+    /// Creates an incremental SHA3-256 hasher.
+    pub fn sha3_256() -> Self {
+        Self::new(512, 0x06, 256 / U8BITS)
+    }
+
+    /// Creates an incremental SHA3-384 hasher.
+    pub fn sha3_384() -> Self {
+        Self::new(768, 0x06, 384 / U8BITS)
+    }
+
+    /// Creates an incremental SHA3-512 hasher.
+    pub fn sha3_512() -> Self {
+        Self::new(1024, 0x06, 512 / U8BITS)
+    }
+
+    /// Creates an incremental SHAKE128 hasher producing `out_len` bytes.
+    pub fn shake128(out_len: usize) -> Self {
+        Self::new(256, 0x1f, out_len)
+    }
+
+    /// Creates an incremental SHAKE256 hasher producing `out_len` bytes.
+    pub fn shake256(out_len: usize) -> Self {
+        Self::new(512, 0x1f, out_len)
+    }
+
+    /// Absorbs more input into the sponge.
+    ///
+    /// Bytes are buffered until a full rate-sized block accumulates, at which
+    /// point the block is XORed into the state and the permutation is applied.
+    pub fn update(&mut self, data: &[u8]) {
+        self.buffer.extend_from_slice(data);
+        while self.buffer.len() >= self.rate {
+            absorb_block(&mut self.state, &self.buffer[..self.rate]);
+            keccak_f(&mut self.state);
+            self.buffer.drain(..self.rate);
         }
-        let (new_x, new_y) = (y, (2 * x + 3 * y) % 5);
-        x = new_x;
-        y = new_y;
     }
 
-    copy_state(state, &new_state);
+    /// Finalizes the sponge and squeezes out the output bytes.
+    ///
+    /// This applies the domain-separation suffix and `pad10*1` padding to the
+    /// trailing partial block, runs a last permutation, and squeezes `out_len`
+    /// bytes out of the state.
+    pub fn finalize(mut self) -> Vec<u8> {
+        let mut last = vec![0u8; self.rate];
+        last[..self.buffer.len()].copy_from_slice(&self.buffer);
+        last[self.buffer.len()] ^= self.suffix;
+        last[self.rate - 1] ^= 0x80;
+        absorb_block(&mut self.state, &last);
+        keccak_f(&mut self.state);
+        squeeze(&mut self.state, self.rate, self.out_len)
+    }
 }
 
-/// The pi step mapping of Keccak.
-///
-/// This function performs the permutation of the state array by swapping the elements
-/// of the state array based on the given indices.
-fn pi(state: &mut State) {
-    let mut new_state = new_state();
-    iterate!(x, y, z => {
-        new_state[x][y][z] = state[(x + 3 * y) % 5][x][z];
-    });
-    copy_state(state, &new_state);
+/// The `left_encode` primitive from NIST SP 800-185.
+///
+/// Encodes the non-negative integer `x` as a byte string whose first byte is
+/// the number of value bytes that follow, with the value itself in big-endian
+/// order. The byte count is placed *before* the value.
+fn left_encode(x: u64) -> Vec<u8> {
+    let be = x.to_be_bytes();
+    let first = be.iter().position(|&b| b != 0).unwrap_or(be.len() - 1);
+    let value = &be[first..];
+    let mut out = Vec::with_capacity(value.len() + 1);
+    out.push(value.len() as u8);
+    out.extend_from_slice(value);
+    out
 }
 
-/// The chi step mapping of Keccak.
+/// The `right_encode` primitive from NIST SP 800-185.
 ///
-/// This function performs the permutation of the state array by performing XOR operations
-/// on the elements of the state array.
-fn chi(state: &mut State) {
-    let mut new_state = new_state();
-    iterate!(x, y, z => {
-        new_state[x][y][z] = state[x][y][z] ^ ((!state[(x + 1) % 5][y][z]) & state[(x + 2) % 5][y][z]);
-    });
-    copy_state(state, &new_state);
+/// Identical to [`left_encode`] except that the byte count is placed *after*
+/// the value bytes.
+fn right_encode(x: u64) -> Vec<u8> {
+    let be = x.to_be_bytes();
+    let first = be.iter().position(|&b| b != 0).unwrap_or(be.len() - 1);
+    let mut out = be[first..].to_vec();
+    out.push((out.len()) as u8);
+    out
 }
 
-/// The iota step mapping of Keccak, incorporating the round constants.
+/// The `encode_string` primitive from NIST SP 800-185.
 ///
-/// This function performs the XOR operation between the state array and the round constants.
-fn iota(state: &mut State, round_index: u8) {
-    let mut rc_arr = [false; W];
-    for j in 0..=L {
-        rc_arr[(1 << j) - 1] = rc(j as u8 + 7 * round_index);
-    }
-    for (z, bit) in rc_arr.iter().enumerate() {
-        state[0][0][z] ^= *bit;
-    }
+/// Prefixes `s` with a `left_encode` of its length *in bits*.
+fn encode_string(s: &[u8]) -> Vec<u8> {
+    let mut out = left_encode(s.len() as u64 * U8BITS as u64);
+    out.extend_from_slice(s);
+    out
 }
 
-/// Computes the round constants for the iota step.
+/// The `bytepad` primitive from NIST SP 800-185.
 ///
-/// This function computes the round constants for the iota step by performing a series
-/// of bitwise operations.
-fn rc(t: u8) -> bool {
-    let mut r: u16 = 0x80;
-    for _ in 0..(t % 255) {
-        r = ((r << 1) ^ ((r >> 7) & 1) * 0x71) & 0xff;
+/// Prepends `left_encode(w)` to `x` and zero-pads the result up to a multiple
+/// of `w` bytes.
+fn bytepad(x: &[u8], w: usize) -> Vec<u8> {
+    let mut out = left_encode(w as u64);
+    out.extend_from_slice(x);
+    while !out.len().is_multiple_of(w) {
+        out.push(0);
     }
-    (r >> 7) & 1 != 0
+    out
 }
 
-/// Performs a single round of the Keccak-f permutation.
+/// The customizable SHAKE (cSHAKE) construction from NIST SP 800-185.
 ///
-/// This function performs a single round of the Keccak-f permutation by calling the
-/// theta, rho, pi, chi, and iota steps.
-fn round(state: &mut State, round_index: u8) {
-    theta(state);
-    rho(state);
-    pi(state);
-    chi(state);
-    iota(state, round_index);
+/// When both the function name and customization string are empty this reduces
+/// to plain SHAKE (domain suffix `1111`). Otherwise the message is prefixed with
+/// `bytepad(encode_string(N) || encode_string(S), rate)` and the cSHAKE domain
+/// suffix `00` is used instead.
+fn cshake(capacity: usize, input: &[u8], out_len: usize, name: &[u8], customization: &[u8]) -> Vec<u8> {
+    if name.is_empty() && customization.is_empty() {
+        return keccak(capacity, 0x1f, input, out_len);
+    }
+    let rate = (B - capacity) / U8BITS;
+    let mut encoded = encode_string(name);
+    encoded.extend(encode_string(customization));
+    let mut msg = bytepad(&encoded, rate);
+    msg.extend_from_slice(input);
+    keccak(capacity, 0x04, &msg, out_len)
 }
 
-/// The Keccak-f permutation function.
-///
-/// This function performs the Keccak-f permutation on the given input bits.
-/// It applies `num_rounds` rounds of the Keccak-f permutation, where
-/// `num_rounds` is calculated based on the number of rounds used in the
-/// Keccak hash function family.
+/// Computes cSHAKE128 of `input`, producing `out_len` bytes.
 ///
 /// # Arguments
 ///
-/// * `bits` - A slice of boolean values representing the input bits.
-///
-/// # Returns
-///
-/// A vector of boolean values representing the output of the Keccak-f
-/// permutation.
-fn keccak_f(bits: &[bool]) -> [bool; B] {
-    // Calculate the number of rounds to be applied
-    let num_rounds = 12 + 2 * L;
-
-    // Create a new state array and fill it with the input bits
-    let mut state = new_state();
-    fill_state(&mut state, bits);
-
-    // Apply the Keccak-f permutation
-    for i in 0..num_rounds {
-        round(&mut state, i as u8);
-    }
+/// * `input` - The message to hash.
+/// * `out_len` - The desired output length in bytes.
+/// * `function_name` - The NIST-defined function name `N` (empty for user use).
+/// * `customization` - The caller-chosen customization string `S`.
+pub fn cshake128(input: &[u8], out_len: usize, function_name: &[u8], customization: &[u8]) -> Vec<u8> {
+    cshake(256, input, out_len, function_name, customization)
+}
 
-    // Dump the state array into a single array of bits
-    dump_state(state)
+/// Computes cSHAKE256 of `input`, producing `out_len` bytes.
+///
+/// See [`cshake128`] for the meaning of the arguments.
+pub fn cshake256(input: &[u8], out_len: usize, function_name: &[u8], customization: &[u8]) -> Vec<u8> {
+    cshake(512, input, out_len, function_name, customization)
 }
 
-/// Pads the input with the `101` pattern according to Keccak specifications.
-fn pad101(x: isize, m: isize) -> Vec<bool> {
-    let j = (x - (m % x) - 2).rem_euclid(x);
-    let mut padding = vec![false; (j + 2) as usize];
-    padding[0] = true;
-    padding[j as usize + 1] = true;
-    padding
+/// The keyed MAC (KMAC) construction from NIST SP 800-185, layered over cSHAKE.
+fn kmac(capacity: usize, key: &[u8], message: &[u8], out_len: usize, customization: &[u8]) -> Vec<u8> {
+    let rate = (B - capacity) / U8BITS;
+    let mut x = bytepad(&encode_string(key), rate);
+    x.extend_from_slice(message);
+    x.extend(right_encode(out_len as u64 * U8BITS as u64));
+    cshake(capacity, &x, out_len, b"KMAC", customization)
 }
 
-/// The sponge construction used in Keccak.
-///
-/// This function implements the sponge construction used in the Keccak hash function.
-/// It takes as input a sponge function `f`, a padding function `pad`, a block size `r`,
-/// an input `n`, and a desired output size `d`. It then iteratively applies the sponge
-/// construction to the input until the desired output size is reached.
+/// Computes KMAC128 over `message` under `key`, producing `out_len` bytes.
 ///
 /// # Arguments
 ///
-/// * `f` - The sponge function to be used.
-/// * `pad` - The padding function to be used.
-/// * `r` - The block size of the sponge function.
-/// * `n` - The input to be processed.
-/// * `d` - The desired output size.
-///
-/// # Returns
-///
-/// A vector of boolean values representing the output of the sponge construction.
-fn sponge(f: SpongeFn, pad: PadFn, r: usize, n: &[bool], d: usize) -> Vec<bool> {
-    // Create a new vector `p` by extending `n` with the result of applying the padding function
-    let mut p = Vec::from(n);
-    p.append(&mut pad(r as isize, n.len() as isize));
-    assert!(r < B);
-
-    // Create a new state `s`
-    let mut s = [false; B];
-
-    // Iterate over the chunks of `p` of size `r`
-    for chunk in p.chunks(r) {
-        // XOR each element of `s` with the corresponding element of `chunk`
-        for (s_i, c_i) in s.iter_mut().zip(chunk) {
-            *s_i ^= *c_i;
-        }
-        // Apply the sponge function `f` to `s`
-        s = f(&s);
-    }
-
-    // Create an empty vector `z`
-    let mut z = Vec::new();
-    // Repeat the following process until `z` has a length of `d`
-    while z.len() < d {
-        // Extend `z` with the elements of `s`
-        z.extend_from_slice(&s);
-        // Apply the sponge function `f` to `s`
-        s = f(&s);
-    }
+/// * `key` - The MAC key.
+/// * `message` - The message to authenticate.
+/// * `out_len` - The desired output length in bytes.
+/// * `customization` - The caller-chosen customization string `S`.
+pub fn kmac128(key: &[u8], message: &[u8], out_len: usize, customization: &[u8]) -> Vec<u8> {
+    kmac(256, key, message, out_len, customization)
+}
 
-    // Truncate `z` to a length of `d`
-    z.truncate(d);
-    // Return `z`
-    z
+/// Computes KMAC256 over `message` under `key`, producing `out_len` bytes.
+///
+/// See [`kmac128`] for the meaning of the arguments.
+pub fn kmac256(key: &[u8], message: &[u8], out_len: usize, customization: &[u8]) -> Vec<u8> {
+    kmac(512, key, message, out_len, customization)
 }
 
-/// The Keccak hash function.
-fn keccak(c: usize, n: &[bool], d: usize) -> Vec<bool> {
-    sponge(keccak_f, pad101, B - c, n, d)
+/// The TupleHash construction from NIST SP 800-185, layered over cSHAKE.
+fn tuplehash(capacity: usize, tuple: &[&[u8]], out_len: usize, customization: &[u8]) -> Vec<u8> {
+    let mut x = Vec::new();
+    for element in tuple {
+        x.extend(encode_string(element));
+    }
+    x.extend(right_encode(out_len as u64 * U8BITS as u64));
+    cshake(capacity, &x, out_len, b"TupleHash", customization)
 }
 
-/// Converts a byte array to a bit array.
+/// Computes TupleHash128 over an ordered `tuple`, producing `out_len` bytes.
 ///
-/// # Arguments
+/// Unlike a plain hash of the concatenated elements, TupleHash unambiguously
+/// encodes each element's length, so distinct tuples never collide.
 ///
-/// * `h` - The byte array to convert.
-/// * `n` - The number of bits to take from the byte array.
-///
-/// # Returns
+/// # Arguments
 ///
-/// A vector of `n` bits.
-fn h2b(h: &[u8], n: usize) -> Vec<bool> {
-    // Map each byte to a vector of its bits, then flatten the result.
-    h.iter()
-        .flat_map(|byte| (0..8).map(move |i| (byte >> i) & 1 == 1))
-        // Take only the first `n` bits.
-        .take(n)
-        // Collect the bits into a vector.
-        .collect()
+/// * `tuple` - The ordered sequence of byte strings to hash.
+/// * `out_len` - The desired output length in bytes.
+/// * `customization` - The caller-chosen customization string `S`.
+pub fn tuplehash128(tuple: &[&[u8]], out_len: usize, customization: &[u8]) -> Vec<u8> {
+    tuplehash(256, tuple, out_len, customization)
 }
 
-/// Converts a bit array to a byte array.
-///
-/// # Arguments
-///
-/// * `s` - The bit array to convert.
-///
-/// # Returns
+/// Computes TupleHash256 over an ordered `tuple`, producing `out_len` bytes.
 ///
-/// A vector of bytes.
-fn b2h(s: &[bool]) -> Vec<u8> {
-    // Chunk the bit array into chunks of 8 bits.
-    s.chunks(U8BITS)
-        // For each chunk, fold the bits into a byte.
-        .map(|chunk| chunk.iter().enumerate().fold(0, |byte, (i, &bit)| byte | ((bit as u8) << i)))
-        // Collect the bytes into a vector.
-        .collect()
+/// See [`tuplehash128`] for the meaning of the arguments.
+pub fn tuplehash256(tuple: &[&[u8]], out_len: usize, customization: &[u8]) -> Vec<u8> {
+    tuplehash(512, tuple, out_len, customization)
 }
 
-/// A macro to define SHA-3 hash functions with different output lengths.
-macro_rules! sha3 {
-    ($name:ident, $n:literal) => {
-        /// Computes the SHA-3 hash of the input data.
-        ///
-        /// # Arguments
-        ///
-        /// * `input` - A byte slice containing the data to hash.
-        ///
-        /// # Returns
-        ///
-        /// A fixed-size array containing the hash output.
-        pub fn $name(input: &[u8]) -> [u8; $n / U8BITS] {
-            let mut bits = h2b(input, input.len() * U8BITS);
-            bits.append(&mut vec![false, true]);
-            let result_bits = keccak($n * 2, &bits, $n);
-            let result_bytes = b2h(&result_bits);
-            result_bytes.try_into().expect("incorrect length")
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Formats a byte slice as a lowercase hex string for comparison.
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn keccak_f_1600_of_zero() {
+        // Known answer for Keccak-f[1600] applied to the all-zero state.
+        let mut state = [0u64; 25];
+        keccak_p(&mut state, 64);
+        let bytes: Vec<u8> = state.iter().flat_map(|lane| lane.to_le_bytes()).collect();
+        assert_eq!(
+            hex(&bytes[..32]),
+            "e7dde140798f25f18a47c033f9ccd584eea95aa61e2698d54d49806f304715bd"
+        );
+    }
+
+    #[test]
+    fn keccak_f_200_of_zero() {
+        // Known answer for the reduced-width Keccak-f[200] applied to the
+        // all-zero state; exercises the masked theta/rho_pi/rotl_w paths.
+        let mut state = [0u64; 25];
+        keccak_p(&mut state, 8);
+        let bytes: Vec<u8> = state.iter().map(|lane| *lane as u8).collect();
+        assert_eq!(
+            hex(&bytes),
+            "3c2826841cb35c171eaae9b811134ceaa3852c69d2c5abafea"
+        );
+    }
+
+    #[test]
+    fn cshake128_nist_sample() {
+        // NIST SP 800-185 cSHAKE128 Sample #1: N = "", S = "Email Signature",
+        // data = 00 01 02 03, output 256 bits.
+        let out = cshake128(&[0x00, 0x01, 0x02, 0x03], 32, b"", b"Email Signature");
+        assert_eq!(
+            hex(&out),
+            "c1c36925b6409a04f1b504fcbca9d82b4017277cb5ed2b2065fc1d3814d5aaf5"
+        );
+    }
+
+    #[test]
+    fn kmac128_nist_sample() {
+        // NIST SP 800-185 KMAC128 Sample #1: key = 0x40..=0x5F, data =
+        // 00 01 02 03, empty customization, output 256 bits.
+        let key: Vec<u8> = (0x40u8..=0x5f).collect();
+        let out = kmac128(&key, &[0x00, 0x01, 0x02, 0x03], 32, b"");
+        assert_eq!(
+            hex(&out),
+            "e5780b0d3ea6f7d3a429c5706aa43a00fadbd7d49628839e3187243f456ee14e"
+        );
+    }
+
+    #[test]
+    fn tuplehash128_nist_sample() {
+        // NIST SP 800-185 TupleHash128 Sample #1: tuple = (000102, 101112131415),
+        // empty customization, output 256 bits.
+        let tuple: [&[u8]; 2] = [&[0x00, 0x01, 0x02], &[0x10, 0x11, 0x12, 0x13, 0x14, 0x15]];
+        let out = tuplehash128(&tuple, 32, b"");
+        assert_eq!(
+            hex(&out),
+            "c5d8786c1afb9b82111ab34b65b2c0048fa64e6d48e263264ce1707d3ffc8ed1"
+        );
+    }
+
+    #[test]
+    fn shake_empty_kats() {
+        assert_eq!(
+            hex(&shake128(b"", 32)),
+            "7f9c2ba4e88f827d616045507605853ed73b8093f6efbc88eb1a6eacfa66ef26"
+        );
+        assert_eq!(
+            hex(&shake256(b"", 32)),
+            "46b9dd2b0ba88d13233b3feb743eeb243fcd52ea62b81b82b50c27646ed5762f"
+        );
+    }
+
+    #[test]
+    fn shake_long_output_re_permutes() {
+        // 200 bytes exceeds the 168-byte SHAKE128 rate, so the squeeze phase
+        // must re-apply the permutation. The output must match the 32-byte KAT
+        // as a prefix and be consistent with shorter requests (XOF property).
+        let long = shake128(b"", 200);
+        assert_eq!(long.len(), 200);
+        assert_eq!(
+            hex(&long[..32]),
+            "7f9c2ba4e88f827d616045507605853ed73b8093f6efbc88eb1a6eacfa66ef26"
+        );
+        assert_eq!(long[..168], shake128(b"", 168)[..]);
+    }
+    #[test]
+    fn hasher_matches_one_shot() {
+        // A split update must produce the same digest as the one-shot function.
+        let mut hasher = Sha3Hasher::sha3_256();
+        hasher.update(b"ab");
+        hasher.update(b"c");
+        let digest = hasher.finalize();
+        assert_eq!(digest[..], sha3_256(b"abc")[..]);
+        assert_eq!(
+            hex(&digest),
+            "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532"
+        );
+    }
+
+    #[test]
+    fn hasher_chunking_independent_of_boundaries() {
+        // Feeding the input in small chunks that straddle the rate block
+        // boundary must match both the one-shot hash and a single update.
+        let data: Vec<u8> = (0..300u32).map(|i| i as u8).collect();
+        let mut chunked = Sha3Hasher::sha3_256();
+        for chunk in data.chunks(7) {
+            chunked.update(chunk);
         }
-    };
-}
+        let mut single = Sha3Hasher::sha3_256();
+        single.update(&data);
+        let expected = sha3_256(&data);
+        let chunked = chunked.finalize();
+        assert_eq!(chunked[..], expected[..]);
+        assert_eq!(single.finalize()[..], expected[..]);
+    }
 
-// Define SHA-3 hash functions with different output lengths.
-sha3!(sha3_224, 224);
-sha3!(sha3_256, 256);
-sha3!(sha3_384, 384);
-sha3!(sha3_512, 512);
+    #[test]
+    fn keccak256_legacy_kats() {
+        // Legacy Ethereum Keccak-256 uses the 0x01 suffix, not SHA-3's 0x06.
+        assert_eq!(
+            hex(&keccak256(b"")),
+            "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+        );
+        assert_eq!(
+            hex(&keccak256(b"abc")),
+            "4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45"
+        );
+    }
+
+}